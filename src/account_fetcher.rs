@@ -0,0 +1,183 @@
+//! Live, websocket-driven cache of clearing house program accounts.
+//!
+//! A `programSubscribe` on the clearing house program plus an
+//! `accountSubscribe` per oracle keep an in-memory map up to date, so the
+//! per-slot loop reads a snapshot instead of polling `getProgramAccounts`.
+
+use log::{debug, warn};
+use solana_account_decoder::UiAccountEncoding;
+use solana_client::{
+    pubsub_client::PubsubClient,
+    rpc_client::RpcClient,
+    rpc_config::{RpcAccountInfoConfig, RpcProgramAccountsConfig},
+};
+use solana_sdk::{account::Account, commitment_config::CommitmentConfig, pubkey::Pubkey};
+use std::{
+    collections::HashMap,
+    str::FromStr,
+    sync::{Arc, RwLock},
+    thread,
+    time::Duration,
+};
+
+/// Snapshot of every clearing house account, refreshed incrementally via
+/// websocket notifications rather than full RPC polling.
+pub struct AccountFetcher {
+    cache: Arc<RwLock<HashMap<Pubkey, Account>>>,
+    slot: Arc<RwLock<u64>>,
+}
+
+impl AccountFetcher {
+    /// Seeds the cache with one `getProgramAccounts` call, then spawns
+    /// background subscriptions that keep it current.
+    pub fn new(
+        ws_endpoint: &str,
+        rpc_client: &RpcClient,
+        program_id: Pubkey,
+        oracles: &[Pubkey],
+    ) -> Result<Self, Box<dyn std::error::Error>> {
+        let cache = Arc::new(RwLock::new(HashMap::new()));
+        let slot = Arc::new(RwLock::new(rpc_client.get_slot()?));
+
+        let fetcher = Self { cache, slot };
+        fetcher.resync(rpc_client, program_id)?;
+
+        spawn_program_subscription(
+            ws_endpoint,
+            rpc_client.url(),
+            program_id,
+            fetcher.cache.clone(),
+            fetcher.slot.clone(),
+        );
+        for oracle in oracles {
+            spawn_account_subscription(ws_endpoint, *oracle, fetcher.cache.clone());
+        }
+
+        Ok(fetcher)
+    }
+
+    /// Full resync via `getProgramAccounts`, used on startup and whenever the
+    /// websocket connection needs to be re-established from a clean slate.
+    pub fn resync(
+        &self,
+        rpc_client: &RpcClient,
+        program_id: Pubkey,
+    ) -> Result<(), Box<dyn std::error::Error>> {
+        resync_cache(rpc_client, program_id, &self.cache)
+    }
+
+    /// Current view of the cache, keyed by account pubkey.
+    pub fn snapshot(&self) -> HashMap<Pubkey, Account> {
+        self.cache.read().unwrap().clone()
+    }
+
+    /// Most recent slot observed by the program subscription (initially the
+    /// slot at startup); oracle updates do not advance it.
+    pub fn slot(&self) -> u64 {
+        *self.slot.read().unwrap()
+    }
+}
+
+fn resync_cache(
+    rpc_client: &RpcClient,
+    program_id: Pubkey,
+    cache: &RwLock<HashMap<Pubkey, Account>>,
+) -> Result<(), Box<dyn std::error::Error>> {
+    let fetched: HashMap<Pubkey, Account> =
+        rpc_client.get_program_accounts(&program_id)?.into_iter().collect();
+    let mut cache = cache.write().unwrap();
+    // only clear out stale program-owned entries; oracle accounts are owned
+    // by Pyth/Switchboard, never appear in get_program_accounts(program_id),
+    // and are kept current by their own accountSubscribe threads, so wiping
+    // the whole cache here would erase them until the next oracle update
+    cache.retain(|pubkey, account| account.owner != program_id || fetched.contains_key(pubkey));
+    let fetched_count = fetched.len();
+    cache.extend(fetched);
+    debug!(
+        "resynced account cache with {} program accounts ({} total cached)",
+        fetched_count,
+        cache.len()
+    );
+    Ok(())
+}
+
+fn subscription_account_config() -> RpcAccountInfoConfig {
+    RpcAccountInfoConfig {
+        encoding: Some(UiAccountEncoding::Base64),
+        commitment: Some(CommitmentConfig::processed()),
+        ..RpcAccountInfoConfig::default()
+    }
+}
+
+fn spawn_program_subscription(
+    ws_endpoint: &str,
+    rpc_endpoint: String,
+    program_id: Pubkey,
+    cache: Arc<RwLock<HashMap<Pubkey, Account>>>,
+    slot: Arc<RwLock<u64>>,
+) {
+    let ws_endpoint = ws_endpoint.to_string();
+    thread::spawn(move || {
+        let rpc_client = RpcClient::new(rpc_endpoint);
+        loop {
+            match PubsubClient::program_subscribe(
+                &ws_endpoint,
+                &program_id,
+                Some(RpcProgramAccountsConfig {
+                    account_config: subscription_account_config(),
+                    ..RpcProgramAccountsConfig::default()
+                }),
+            ) {
+                Ok((_subscription, receiver)) => {
+                    for update in receiver {
+                        *slot.write().unwrap() = update.context.slot;
+                        if let Ok(pubkey) = Pubkey::from_str(&update.value.pubkey) {
+                            if let Some(account) = update.value.account.decode::<Account>() {
+                                cache.write().unwrap().insert(pubkey, account);
+                            }
+                        }
+                    }
+                    warn!("program subscription stream ended, resubscribing");
+                }
+                Err(err) => {
+                    warn!("program subscribe failed, retrying: {:?}", err);
+                }
+            }
+            // the stream may have dropped accounts that changed while it was
+            // down, and programSubscribe doesn't replay missed state, so
+            // catch back up before resubscribing
+            if let Err(err) = resync_cache(&rpc_client, program_id, &cache) {
+                warn!("resync after dropped program subscription failed: {:?}", err);
+            }
+            thread::sleep(Duration::from_secs(1));
+        }
+    });
+}
+
+fn spawn_account_subscription(
+    ws_endpoint: &str,
+    pubkey: Pubkey,
+    cache: Arc<RwLock<HashMap<Pubkey, Account>>>,
+) {
+    let ws_endpoint = ws_endpoint.to_string();
+    thread::spawn(move || loop {
+        match PubsubClient::account_subscribe(
+            &ws_endpoint,
+            &pubkey,
+            Some(subscription_account_config()),
+        ) {
+            Ok((_subscription, receiver)) => {
+                for update in receiver {
+                    if let Some(account) = update.value.decode::<Account>() {
+                        cache.write().unwrap().insert(pubkey, account);
+                    }
+                }
+                warn!("oracle subscription for {} ended, resubscribing", pubkey);
+            }
+            Err(err) => {
+                warn!("account subscribe for {} failed, retrying: {:?}", pubkey, err);
+            }
+        }
+        thread::sleep(Duration::from_secs(1));
+    });
+}
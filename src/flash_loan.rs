@@ -0,0 +1,256 @@
+//! Flash-loan-funded liquidation: borrow the quote asset to cover it, run
+//! the liquidation, swap seized collateral back to quote via Jupiter, and
+//! repay -- all in one transaction, so the liquidator needs no pre-funded
+//! collateral.
+
+use log::{debug, warn};
+use serde::Deserialize;
+use solana_sdk::{
+    instruction::AccountMeta, instruction::Instruction, program_pack::Pack, pubkey::Pubkey,
+};
+use std::error::Error;
+
+/// Solend's flash loan program: borrow and repay must appear in the same
+/// transaction or the program aborts it, which is what makes the capital
+/// requirement disappear.
+pub const SOLEND_PROGRAM_ID: Pubkey =
+    solana_sdk::pubkey!("So1endDq2YkqhipRh3WViPa8hdiSpxWy6z3Z6tMCpAo");
+
+/// Borrows `amount` of a Solend reserve's liquidity into
+/// `destination_liquidity`; must be paired with [`repay`] later in the same
+/// transaction.
+pub fn borrow(
+    reserve: Pubkey,
+    reserve_liquidity_supply: Pubkey,
+    destination_liquidity: Pubkey,
+    lending_market: Pubkey,
+    lending_market_authority: Pubkey,
+    amount: u64,
+) -> Instruction {
+    let mut data = vec![12u8]; // FlashBorrowReserveLiquidity
+    data.extend_from_slice(&amount.to_le_bytes());
+    Instruction {
+        program_id: SOLEND_PROGRAM_ID,
+        accounts: vec![
+            AccountMeta::new(reserve_liquidity_supply, false),
+            AccountMeta::new(destination_liquidity, false),
+            AccountMeta::new(reserve, false),
+            AccountMeta::new_readonly(lending_market, false),
+            AccountMeta::new_readonly(lending_market_authority, false),
+            AccountMeta::new_readonly(spl_token::id(), false),
+        ],
+        data,
+    }
+}
+
+/// Repays `amount` from `source_liquidity`; callers pass the principal of a
+/// preceding [`borrow`] plus the reserve's flash loan fee.
+pub fn repay(
+    source_liquidity: Pubkey,
+    destination_liquidity_supply: Pubkey,
+    reserve: Pubkey,
+    fee_receiver: Pubkey,
+    lending_market: Pubkey,
+    transfer_authority: Pubkey,
+    amount: u64,
+) -> Instruction {
+    let mut data = vec![13u8]; // FlashRepayReserveLiquidity
+    data.extend_from_slice(&amount.to_le_bytes());
+    Instruction {
+        program_id: SOLEND_PROGRAM_ID,
+        accounts: vec![
+            AccountMeta::new(source_liquidity, false),
+            AccountMeta::new(destination_liquidity_supply, false),
+            AccountMeta::new(reserve, false),
+            AccountMeta::new(fee_receiver, false),
+            AccountMeta::new_readonly(lending_market, false),
+            AccountMeta::new_readonly(transfer_authority, true),
+            AccountMeta::new_readonly(spl_token::id(), false),
+        ],
+        data,
+    }
+}
+
+#[derive(Debug, Deserialize)]
+struct JupiterQuoteResponse {
+    #[serde(rename = "outAmount")]
+    out_amount: String,
+    #[serde(rename = "routePlan")]
+    route_plan: serde_json::Value,
+}
+
+#[derive(Debug, Deserialize)]
+struct JupiterSwapInstructionsResponse {
+    #[serde(rename = "swapInstruction")]
+    swap_instruction: JupiterInstructionPayload,
+}
+
+#[derive(Debug, Deserialize)]
+struct JupiterInstructionPayload {
+    #[serde(rename = "programId")]
+    program_id: String,
+    accounts: Vec<JupiterAccountMeta>,
+    data: String,
+}
+
+#[derive(Debug, Deserialize)]
+struct JupiterAccountMeta {
+    pubkey: String,
+    #[serde(rename = "isSigner")]
+    is_signer: bool,
+    #[serde(rename = "isWritable")]
+    is_writable: bool,
+}
+
+/// A Jupiter aggregator quote for swapping `input_mint` into `output_mint`,
+/// along with the instruction that executes it.
+pub struct JupiterRoute {
+    pub out_amount: u64,
+    instruction: Instruction,
+}
+
+/// Fetches the best Jupiter route for `amount` of `input_mint` into
+/// `output_mint` and the instruction that executes it, so the seized
+/// collateral can be swapped back to quote within the liquidation
+/// transaction.
+pub fn fetch_route(
+    input_mint: Pubkey,
+    output_mint: Pubkey,
+    amount: u64,
+    user_public_key: Pubkey,
+) -> Result<JupiterRoute, Box<dyn Error>> {
+    let quote: JupiterQuoteResponse = reqwest::blocking::get(format!(
+        "https://quote-api.jup.ag/v6/quote?inputMint={}&outputMint={}&amount={}&slippageBps=50",
+        input_mint, output_mint, amount
+    ))?
+    .json()?;
+
+    let swap_instructions: JupiterSwapInstructionsResponse = reqwest::blocking::Client::new()
+        .post("https://quote-api.jup.ag/v6/swap-instructions")
+        .json(&serde_json::json!({
+            "quoteResponse": {
+                "outAmount": quote.out_amount,
+                "routePlan": quote.route_plan,
+            },
+            "userPublicKey": user_public_key.to_string(),
+        }))
+        .send()?
+        .json()?;
+
+    let payload = swap_instructions.swap_instruction;
+    let instruction = Instruction {
+        program_id: payload.program_id.parse()?,
+        accounts: payload
+            .accounts
+            .into_iter()
+            .map(|meta| AccountMeta {
+                pubkey: meta.pubkey.parse().unwrap(),
+                is_signer: meta.is_signer,
+                is_writable: meta.is_writable,
+            })
+            .collect(),
+        data: base64::decode(payload.data)?,
+    };
+
+    debug!(
+        "jupiter route {} -> {} out_amount={}",
+        input_mint, output_mint, quote.out_amount
+    );
+
+    Ok(JupiterRoute {
+        out_amount: quote.out_amount.parse()?,
+        instruction,
+    })
+}
+
+impl JupiterRoute {
+    pub fn into_instruction(self) -> Instruction {
+        self.instruction
+    }
+}
+
+/// Checks a `simulate_with_accounts` result for whether the borrow, swap and
+/// repay clears `min_profit_bps` against `repay_amount`. The borrow and
+/// repay cancel out except for the reserve's fee, so the simulated
+/// liquidity account's balance minus its pre-transaction balance is the
+/// realized profit directly. Returns `false`, not an error, on anything that
+/// simulated badly or fell short, so callers can just skip sending.
+pub fn check_profit(
+    simulation: &solana_client::rpc_response::RpcSimulateTransactionResult,
+    pre_balance: u64,
+    repay_amount: u64,
+    min_profit_bps: i64,
+) -> bool {
+    if let Some(err) = &simulation.err {
+        warn!("flash loan liquidation simulated to an error: {:?}", err);
+        return false;
+    }
+
+    let post_balance = match simulation
+        .accounts
+        .as_ref()
+        .and_then(|accounts| accounts.first())
+        .and_then(|account| account.as_ref())
+        .and_then(|account| account.decode::<solana_sdk::account::Account>())
+        .and_then(|account| spl_token::state::Account::unpack(&account.data).ok())
+    {
+        Some(account) => account.amount,
+        None => {
+            warn!("flash loan liquidation simulation didn't return the liquidity account's post-swap balance");
+            return false;
+        }
+    };
+
+    let profit = post_balance as i128 - pre_balance as i128;
+    let profit_bps = (profit * 10_000 / repay_amount.max(1) as i128) as i64;
+    if profit_bps < min_profit_bps {
+        debug!(
+            "flash loan liquidation profit {} bps below threshold {} bps",
+            profit_bps, min_profit_bps
+        );
+        return false;
+    }
+
+    true
+}
+
+/// Assembles the full flash-loan-funded liquidation: borrow the quote
+/// asset, run the liquidate instruction, swap the seized collateral back to
+/// quote, then repay the borrow plus fee. All of this lands in one
+/// transaction or none of it does.
+#[allow(clippy::too_many_arguments)]
+pub fn build_instructions(
+    borrow_amount: u64,
+    repay_amount: u64,
+    reserve: Pubkey,
+    reserve_liquidity_supply: Pubkey,
+    fee_receiver: Pubkey,
+    lending_market: Pubkey,
+    lending_market_authority: Pubkey,
+    liquidator_liquidity_account: Pubkey,
+    liquidator: Pubkey,
+    liquidate_instruction: Instruction,
+    rebalance_route: JupiterRoute,
+) -> Vec<Instruction> {
+    vec![
+        borrow(
+            reserve,
+            reserve_liquidity_supply,
+            liquidator_liquidity_account,
+            lending_market,
+            lending_market_authority,
+            borrow_amount,
+        ),
+        liquidate_instruction,
+        rebalance_route.into_instruction(),
+        repay(
+            liquidator_liquidity_account,
+            reserve_liquidity_supply,
+            reserve,
+            fee_receiver,
+            lending_market,
+            liquidator,
+            repay_amount,
+        ),
+    ]
+}
@@ -0,0 +1,171 @@
+//! Address Lookup Table support for versioned (v0) transactions, so a user
+//! with many open positions doesn't blow the legacy transaction size limit.
+
+use log::info;
+use solana_address_lookup_table_program::{instruction as alt_instruction, state::AddressLookupTable};
+use solana_account_decoder::UiAccountEncoding;
+use solana_client::{
+    client_error::Result as ClientResult,
+    rpc_client::RpcClient,
+    rpc_config::{RpcSimulateTransactionAccountsConfig, RpcSimulateTransactionConfig},
+    rpc_response::RpcSimulateTransactionResult,
+};
+use solana_sdk::{
+    address_lookup_table_account::AddressLookupTableAccount,
+    hash::Hash,
+    instruction::Instruction,
+    message::{v0, VersionedMessage},
+    pubkey::Pubkey,
+    signature::{Keypair, Signature},
+    signer::Signer,
+    transaction::{Transaction, VersionedTransaction},
+};
+use std::error::Error;
+
+/// Either a legacy transaction or a v0 transaction backed by an address
+/// lookup table, depending on whether `--lookup-table` is configured.
+pub enum LiquidatorTransaction {
+    Legacy(Transaction),
+    Versioned(VersionedTransaction),
+}
+
+impl LiquidatorTransaction {
+    pub fn send(&self, client: &RpcClient) -> ClientResult<Signature> {
+        match self {
+            LiquidatorTransaction::Legacy(tx) => client.send_transaction(tx),
+            LiquidatorTransaction::Versioned(tx) => client.send_transaction(tx),
+        }
+    }
+
+    /// Dry-runs the transaction with `simulateTransaction` without sending
+    /// it, so a caller can inspect logs/return data (e.g. to estimate
+    /// realized profit) before committing to a send.
+    pub fn simulate(&self, client: &RpcClient) -> ClientResult<RpcSimulateTransactionResult> {
+        match self {
+            LiquidatorTransaction::Legacy(tx) => {
+                client.simulate_transaction(tx).map(|response| response.value)
+            }
+            LiquidatorTransaction::Versioned(tx) => {
+                client.simulate_transaction(tx).map(|response| response.value)
+            }
+        }
+    }
+
+    /// Same as [`Self::simulate`], but also returns the post-simulation
+    /// state of `addresses` so a caller can read back a token balance
+    /// changed mid-transaction (e.g. a flash loan's swap proceeds) without a
+    /// second round trip.
+    pub fn simulate_with_accounts(
+        &self,
+        client: &RpcClient,
+        addresses: &[Pubkey],
+    ) -> ClientResult<RpcSimulateTransactionResult> {
+        let config = RpcSimulateTransactionConfig {
+            accounts: Some(RpcSimulateTransactionAccountsConfig {
+                encoding: Some(UiAccountEncoding::Base64),
+                addresses: addresses.iter().map(|pubkey| pubkey.to_string()).collect(),
+            }),
+            ..RpcSimulateTransactionConfig::default()
+        };
+        match self {
+            LiquidatorTransaction::Legacy(tx) => client
+                .simulate_transaction_with_config(tx, config)
+                .map(|response| response.value),
+            LiquidatorTransaction::Versioned(tx) => client
+                .simulate_transaction_with_config(tx, config)
+                .map(|response| response.value),
+        }
+    }
+}
+
+/// Builds a transaction for `instructions`, compiling a v0 message against
+/// `lookup_table` when one is configured and falling back to a legacy
+/// transaction otherwise.
+pub fn build_transaction(
+    instructions: &[Instruction],
+    payer: &Keypair,
+    recent_blockhash: Hash,
+    lookup_table: Option<&AddressLookupTableAccount>,
+) -> Result<LiquidatorTransaction, Box<dyn Error>> {
+    match lookup_table {
+        Some(lookup_table) => {
+            let message = v0::Message::try_compile(
+                &payer.pubkey(),
+                instructions,
+                &[lookup_table.clone()],
+                recent_blockhash,
+            )?;
+            let transaction =
+                VersionedTransaction::try_new(VersionedMessage::V0(message), &[payer])?;
+            Ok(LiquidatorTransaction::Versioned(transaction))
+        }
+        None => Ok(LiquidatorTransaction::Legacy(
+            Transaction::new_signed_with_payer(
+                instructions,
+                Some(&payer.pubkey()),
+                &[payer],
+                recent_blockhash,
+            ),
+        )),
+    }
+}
+
+/// One-time `--init-lookup-table` setup: creates an ALT and extends it with
+/// the state account, markets, both vaults and authorities, the trade,
+/// liquidation and funding histories, and every market oracle.
+pub fn init_lookup_table(
+    client: &RpcClient,
+    payer: &Keypair,
+    state: Pubkey,
+    markets: Pubkey,
+    order_state: Pubkey,
+    vaults_and_authorities: &[Pubkey],
+    histories: &[Pubkey],
+    oracles: &[Pubkey],
+) -> Result<Pubkey, Box<dyn Error>> {
+    let recent_slot = client.get_slot()?;
+    let (create_ix, lookup_table_address) =
+        alt_instruction::create_lookup_table(payer.pubkey(), payer.pubkey(), recent_slot);
+
+    let mut addresses = vec![state, markets, order_state];
+    addresses.extend_from_slice(vaults_and_authorities);
+    addresses.extend_from_slice(histories);
+    addresses.extend_from_slice(oracles);
+    let address_count = addresses.len();
+
+    let extend_ix = alt_instruction::extend_lookup_table(
+        lookup_table_address,
+        payer.pubkey(),
+        Some(payer.pubkey()),
+        addresses,
+    );
+
+    let blockhash = client.get_latest_blockhash()?;
+    let transaction = Transaction::new_signed_with_payer(
+        &[create_ix, extend_ix],
+        Some(&payer.pubkey()),
+        &[payer],
+        blockhash,
+    );
+    let signature = client.send_and_confirm_transaction(&transaction)?;
+    info!(
+        "created lookup table {} with {} addresses in {}",
+        lookup_table_address, address_count, signature
+    );
+
+    Ok(lookup_table_address)
+}
+
+/// Fetches and deserializes an existing lookup table for use when building
+/// versioned transactions.
+pub fn fetch_lookup_table(
+    client: &RpcClient,
+    lookup_table_address: Pubkey,
+) -> Result<AddressLookupTableAccount, Box<dyn Error>> {
+    let account = client.get_account(&lookup_table_address)?;
+    let lookup_table = AddressLookupTable::deserialize(&account.data)?;
+    Ok(AddressLookupTableAccount {
+        key: lookup_table_address,
+        addresses: lookup_table.addresses.to_vec(),
+    })
+}
@@ -1,3 +1,12 @@
+mod account_fetcher;
+mod flash_loan;
+mod lookup_table;
+mod oracle;
+mod scheduler;
+mod telemetry;
+mod tx_sender;
+
+use account_fetcher::AccountFetcher;
 use anchor_lang::{AccountDeserialize, InstructionData, ToAccountMetas};
 use clap::Parser;
 use clearing_house::{
@@ -18,22 +27,22 @@ use clearing_house::{
         user_orders::{OrderStatus, UserOrders},
     },
 };
-use log::{debug, info};
+use log::{debug, info, warn};
 use rayon::iter::{IntoParallelRefIterator, IntoParallelRefMutIterator, ParallelIterator};
 use solana_client::rpc_client::RpcClient;
 use solana_sdk::{account::Account, account_info::IntoAccountInfo};
 use solana_sdk::{
+    address_lookup_table_account::AddressLookupTableAccount,
     commitment_config::CommitmentConfig,
     instruction::{AccountMeta, Instruction},
     pubkey::Pubkey,
     signature::Keypair,
     signer::Signer,
-    transaction::Transaction,
 };
 use std::{
     cell::RefCell,
     cmp::min,
-    collections::HashMap,
+    collections::{HashMap, HashSet},
     env,
     error::Error,
     fs::File,
@@ -53,13 +62,127 @@ struct Args {
     #[clap(short, long, default_value = "https://ssc-dao.genesysgo.net")]
     endpoint: String,
 
+    /// Websocket endpoint for the account cache; defaults to --endpoint's ws(s) equivalent
+    #[clap(long)]
+    ws_endpoint: Option<String>,
+
+    /// Existing lookup table to build versioned transactions against; omit for legacy transactions
+    #[clap(long)]
+    lookup_table: Option<Pubkey>,
+
+    /// Create a lookup table covering the fixed accounts and every oracle, print its address, and exit
+    #[clap(long)]
+    init_lookup_table: bool,
+
+    /// Max oracle staleness, in slots, on top of the protocol's own guard rail
+    #[clap(long, default_value = "25")]
+    max_oracle_staleness_slots: i64,
+
+    /// Seconds to wait for a submitted transaction to confirm before it's eligible for retry
+    #[clap(long, default_value = "30")]
+    confirmation_timeout_secs: u64,
+
+    /// Max retries for a timed-out transaction before giving up on it
+    #[clap(long, default_value = "3")]
+    max_retries: u32,
+
+    /// Fund liquidations with a Solend flash loan instead of pre-funded collateral
+    #[clap(long)]
+    flash_loan: bool,
+
+    /// Minimum net profit, in bps of the repaid flash loan amount, to send a liquidation
+    #[clap(long, default_value = "10")]
+    min_profit_bps: i64,
+
+    /// Solend reserve to borrow the quote asset from
+    #[clap(long, requires = "flash_loan")]
+    solend_reserve: Option<Pubkey>,
+
+    /// Solend reserve liquidity supply account
+    #[clap(long, requires = "flash_loan")]
+    solend_reserve_liquidity_supply: Option<Pubkey>,
+
+    /// Solend flash loan fee receiver
+    #[clap(long, requires = "flash_loan")]
+    solend_fee_receiver: Option<Pubkey>,
+
+    /// Solend lending market
+    #[clap(long, requires = "flash_loan")]
+    solend_lending_market: Option<Pubkey>,
+
+    /// Solend lending market authority PDA
+    #[clap(long, requires = "flash_loan")]
+    solend_lending_market_authority: Option<Pubkey>,
+
+    /// Liquidator token account that receives the flash-borrowed quote asset and funds repayment
+    #[clap(long, requires = "flash_loan")]
+    liquidator_liquidity_account: Option<Pubkey>,
+
+    /// Quote mint to borrow, repay, and swap seized collateral into
+    #[clap(long, requires = "flash_loan")]
+    quote_mint: Option<Pubkey>,
+
+    /// Amount of the quote asset to borrow, in its native units
+    #[clap(long, requires = "flash_loan")]
+    flash_loan_amount: Option<u64>,
+
+    /// Solend reserve's flash loan fee, in bps of the borrowed amount
+    #[clap(long, default_value = "0", requires = "flash_loan")]
+    solend_flash_loan_fee_bps: u64,
+
+    /// Max liquidations submitted per slot, highest expected payout first; 0 means no cap
+    #[clap(long, default_value = "0")]
+    max_liquidations_per_slot: usize,
+
+    /// Append a JSONL telemetry event for every crank and liquidation to this path
+    #[clap(long)]
+    telemetry_path: Option<String>,
+
+    /// Serve liquidation counters/gauges as Prometheus text on this port
+    #[clap(long)]
+    metrics_port: Option<u16>,
+
     /// Enable verbose logging
     #[clap(short, long)]
     verbose: bool,
 }
 
+/// Accounts and amount needed to fund a liquidation via a Solend flash loan.
+struct FlashLoanAccounts {
+    reserve: Pubkey,
+    reserve_liquidity_supply: Pubkey,
+    fee_receiver: Pubkey,
+    lending_market: Pubkey,
+    lending_market_authority: Pubkey,
+    liquidator_liquidity_account: Pubkey,
+    quote_mint: Pubkey,
+    amount: u64,
+    fee_bps: u64,
+}
+
+impl FlashLoanAccounts {
+    /// The amount owed back to the reserve: principal plus the reserve's
+    /// flash loan fee.
+    fn repay_amount(&self) -> u64 {
+        self.amount + self.amount * self.fee_bps / 10_000
+    }
+}
+
+/// Flat estimate of a liquidation transaction's network fee, in lamports.
+const ESTIMATED_TRANSACTION_FEE: u64 = 5_000;
+
+fn default_ws_endpoint(rpc_endpoint: &str) -> String {
+    rpc_endpoint
+        .replacen("https://", "wss://", 1)
+        .replacen("http://", "ws://", 1)
+}
+
 fn main() -> Result<(), Box<dyn Error>> {
     let args = Args::parse();
+    let ws_endpoint = args
+        .ws_endpoint
+        .clone()
+        .unwrap_or_else(|| default_ws_endpoint(&args.endpoint));
 
     if env::var("RUST_LOG").is_err() {
         env::set_var("RUST_LOG", if args.verbose { "debug" } else { "info" })
@@ -132,6 +255,74 @@ fn main() -> Result<(), Box<dyn Error>> {
         elapsed
     );
 
+    let oracles: Vec<Pubkey> = markets
+        .1
+        .markets
+        .iter()
+        .map(|market| market.amm.oracle)
+        .filter(|oracle| *oracle != Pubkey::default())
+        .collect();
+
+    if args.init_lookup_table {
+        let lookup_table_address = lookup_table::init_lookup_table(
+            &client,
+            &payer,
+            state.0,
+            markets.0,
+            order_state.0,
+            &[
+                state.1.collateral_vault,
+                state.1.collateral_vault_authority,
+                state.1.insurance_vault,
+                state.1.insurance_vault_authority,
+            ],
+            &[
+                state.1.trade_history,
+                state.1.liquidation_history,
+                state.1.funding_payment_history,
+                state.1.funding_rate_history,
+                state.1.extended_curve_history,
+            ],
+            &oracles,
+        )?;
+        info!("pass --lookup-table {} to use it", lookup_table_address);
+        return Ok(());
+    }
+
+    let lookup_table_account: Option<AddressLookupTableAccount> = match args.lookup_table {
+        Some(lookup_table_address) => {
+            Some(lookup_table::fetch_lookup_table(&client, lookup_table_address)?)
+        }
+        None => None,
+    };
+
+    let account_fetcher =
+        AccountFetcher::new(&ws_endpoint, &client, clearing_house::id(), &oracles)?;
+
+    let flash_loan_accounts = if args.flash_loan {
+        Some(FlashLoanAccounts {
+            reserve: args.solend_reserve.unwrap(),
+            reserve_liquidity_supply: args.solend_reserve_liquidity_supply.unwrap(),
+            fee_receiver: args.solend_fee_receiver.unwrap(),
+            lending_market: args.solend_lending_market.unwrap(),
+            lending_market_authority: args.solend_lending_market_authority.unwrap(),
+            liquidator_liquidity_account: args.liquidator_liquidity_account.unwrap(),
+            quote_mint: args.quote_mint.unwrap(),
+            amount: args.flash_loan_amount.unwrap(),
+            fee_bps: args.solend_flash_loan_fee_bps,
+        })
+    } else {
+        None
+    };
+
+    let transaction_tracker = tx_sender::TransactionTracker::new(
+        CommitmentConfig::confirmed(),
+        args.max_retries,
+        Duration::from_secs(args.confirmation_timeout_secs),
+    );
+
+    let telemetry = telemetry::Telemetry::new(args.telemetry_path.as_deref(), args.metrics_port)?;
+
     let mut slot = client.get_slot()?;
     loop {
         while client.get_slot()? == slot {
@@ -142,40 +333,92 @@ fn main() -> Result<(), Box<dyn Error>> {
 
         let start = Instant::now();
 
-        let mut data_map: HashMap<Pubkey, Account> = HashMap::new();
+        transaction_tracker.poll(&client, &telemetry);
 
-        let account_data: Vec<(Pubkey, Account)> =
-            client.get_program_accounts(&clearing_house::id()).unwrap();
-        for (pubkey, account) in account_data.into_iter() {
-            assert!(!data_map.contains_key(&pubkey));
-            data_map.insert(pubkey, account);
-        }
+        // live cache kept current by the account fetcher's subscriptions;
+        // only a startup/resync path falls back to get_program_accounts
+        let data_map: HashMap<Pubkey, Account> = account_fetcher.snapshot();
 
-        // reload markets and funding payment history and oracles
+        // reload markets and funding payment history from the cache
         markets = (
             markets.0,
-            Markets::try_deserialize(&mut &*client.get_account_data(&markets.0).unwrap()).unwrap(),
+            Markets::try_deserialize(&mut &*data_map.get(&markets.0).unwrap().data.clone())
+                .unwrap(),
         );
 
-        let funding_payment_history_data =
-            client.get_account_data(&state.1.funding_payment_history)?;
+        let funding_payment_history_data = data_map
+            .get(&state.1.funding_payment_history)
+            .unwrap()
+            .data
+            .clone();
 
         let oracle_accounts = Mutex::new(vec![]);
+        let mut missing_oracles = false;
         markets.1.markets.par_iter().for_each(|market| {
             if market.amm.oracle != Pubkey::default() {
-                let account = client.get_account(&market.amm.oracle).unwrap();
-                oracle_accounts.lock().unwrap().push((
-                    market.amm.oracle,
-                    account,
-                ));
+                if let Some(account) = data_map.get(&market.amm.oracle) {
+                    oracle_accounts
+                        .lock()
+                        .unwrap()
+                        .push((market.amm.oracle, account.clone()));
+                } else {
+                    debug!("no cached account for oracle {}, resyncing", market.amm.oracle);
+                }
             }
         });
-        let oracle_accounts = oracle_accounts.into_inner().unwrap();
+        let mut oracle_accounts = oracle_accounts.into_inner().unwrap();
+        for market in markets.1.markets.iter() {
+            if market.amm.oracle != Pubkey::default() && !data_map.contains_key(&market.amm.oracle)
+            {
+                missing_oracles = true;
+            }
+        }
+        if missing_oracles {
+            match account_fetcher.resync(&client, clearing_house::id()) {
+                Ok(()) => {
+                    let data_map = account_fetcher.snapshot();
+                    for market in markets.1.markets.iter() {
+                        if market.amm.oracle != Pubkey::default()
+                            && !oracle_accounts.iter().any(|(pubkey, _)| *pubkey == market.amm.oracle)
+                        {
+                            if let Some(account) = data_map.get(&market.amm.oracle) {
+                                oracle_accounts.push((market.amm.oracle, account.clone()));
+                            }
+                        }
+                    }
+                }
+                Err(err) => warn!("cache-miss resync failed: {:?}", err),
+            }
+        }
+
+        // validate every oracle once per slot rather than per user; markets
+        // whose oracle fails staleness or confidence checks are excluded
+        // from cranking and liquidation below instead of panicking
+        let mut invalid_oracles: HashSet<Pubkey> = HashSet::new();
+        let mut oracle_validation_accounts = oracle_accounts.clone();
+        for oracle_account in oracle_validation_accounts.iter_mut() {
+            let oracle_key = oracle_account.0;
+            let market = match markets.1.markets.iter().find(|m| m.amm.oracle == oracle_key) {
+                Some(market) => market,
+                None => continue,
+            };
+            let oracle_info = oracle_account.into_account_info();
+            if let Err(err) = oracle::validate_oracle(
+                &oracle_info,
+                market,
+                slot,
+                args.max_oracle_staleness_slots,
+                &state.1.oracle_guard_rails,
+            ) {
+                warn!("excluding oracle {} for this slot: {}", oracle_key, err);
+                invalid_oracles.insert(oracle_key);
+            }
+        }
 
         // loop over all users
-        let min_margin = users
+        let slot_results: Vec<(u128, Option<scheduler::LiquidationCandidate>)> = users
             .par_iter_mut()
-            .filter_map(|mut user| -> Option<u128> {
+            .filter_map(|mut user| -> Option<(u128, Option<scheduler::LiquidationCandidate>)> {
                 // place holder account info
                 let mut oracles = vec![];
                 let mut cloned_oracle_accounts = oracle_accounts.clone();
@@ -212,7 +455,9 @@ fn main() -> Result<(), Box<dyn Error>> {
 
                 // crank limit orders
                 let orders_account = orders.get(&user.0);
-                if orders_account.is_some() {
+                if orders_account.is_some()
+                    && !transaction_tracker.is_pending(user.0, tx_sender::Action::FillOrder)
+                {
                     let orders_account = orders_account.unwrap();
 
                     for order in &orders_account.1.orders {
@@ -223,6 +468,14 @@ fn main() -> Result<(), Box<dyn Error>> {
                         }
                         let order_market = markets.1.get_market(order.market_index);
 
+                        if invalid_oracles.contains(&order_market.amm.oracle) {
+                            debug!(
+                                "skipping order on market {} with invalid oracle",
+                                order.market_index
+                            );
+                            continue;
+                        }
+
                         let mut oracle = None;
                         for o in &oracles {
                             if *o.key == order_market.amm.oracle {
@@ -230,11 +483,21 @@ fn main() -> Result<(), Box<dyn Error>> {
                                 break;
                             }
                         }
-
-                        let oracle_price = order_market
-                            .amm
-                            .get_oracle_price(oracle.unwrap(), slot)
-                            .unwrap();
+                        let oracle = match oracle {
+                            Some(oracle) => oracle,
+                            None => continue,
+                        };
+
+                        let oracle_price = match order_market.amm.get_oracle_price(oracle, slot) {
+                            Ok(oracle_price) => oracle_price,
+                            Err(err) => {
+                                debug!(
+                                    "skipping order on market {}: {:?}",
+                                    order.market_index, err
+                                );
+                                continue;
+                            }
+                        };
                         let fillable_amount_user = calculate_base_asset_amount_user_can_execute(
                             &mut user.1,
                             &mut user_positions.borrow_mut(),
@@ -304,19 +567,56 @@ fn main() -> Result<(), Box<dyn Error>> {
                                 .data(),
                             };
 
-                            info!(
-                                "result: {:?}",
-                                client.send_transaction(&Transaction::new_signed_with_payer(
-                                    &vec![crank_instruction],
-                                    Some(&payer.pubkey()),
-                                    &vec![&payer],
-                                    recent_blockhash
-                                ))
+                            let crank_transaction = lookup_table::build_transaction(
+                                &[crank_instruction],
+                                &payer,
+                                recent_blockhash,
+                                lookup_table_account.as_ref(),
                             );
+                            match crank_transaction {
+                                Ok(crank_transaction) => {
+                                    match crank_transaction.send(&client) {
+                                        Ok(signature) => {
+                                            info!("result: {:?}", signature);
+                                            transaction_tracker.track(
+                                                user.0,
+                                                tx_sender::Action::FillOrder,
+                                                signature,
+                                                slot,
+                                                0,
+                                                Some(order.market_index as u64),
+                                                0,
+                                            );
+                                        }
+                                        Err(err) => info!("result: {:?}", err),
+                                    }
+                                }
+                                Err(err) => {
+                                    info!("failed to build crank transaction: {:?}", err);
+                                }
+                            }
                         }
                     }
                 }
 
+                // every market the user holds a position in needs a valid
+                // oracle before its liquidation status can be trusted
+                for position in user_positions.borrow().positions {
+                    if position.base_asset_amount == 0 {
+                        continue;
+                    }
+                    let oracle = markets_account.borrow().markets[position.market_index as usize]
+                        .amm
+                        .oracle;
+                    if invalid_oracles.contains(&oracle) {
+                        warn!(
+                            "skipping user {} this slot: invalid oracle for market {}",
+                            user.0, position.market_index
+                        );
+                        return None;
+                    }
+                }
+
                 // Verify that the user is in liquidation territory
                 let liquidation_status = calculate_liquidation_status(
                     &user.1,
@@ -332,8 +632,12 @@ fn main() -> Result<(), Box<dyn Error>> {
                 }
                 let liquidation_status = liquidation_status.unwrap();
 
-                // is liquidatable
-                if liquidation_status.liquidation_type != LiquidationType::NONE {
+                // is liquidatable, and no liquidation for this user is
+                // already inflight and unconfirmed; build the candidate now
+                // but leave sending it to the ranked scheduling pass below
+                let candidate = if liquidation_status.liquidation_type != LiquidationType::NONE
+                    && !transaction_tracker.is_pending(user.0, tx_sender::Action::Liquidate)
+                {
                     let mut accounts = vec![
                         AccountMeta::new_readonly(state.0, false),
                         AccountMeta::new_readonly(payer.pubkey(), true),
@@ -365,29 +669,221 @@ fn main() -> Result<(), Box<dyn Error>> {
                         data: hex::decode("dfb3e27d302e274a").unwrap(),
                     };
 
-                    let liquidate_transaction = Transaction::new_signed_with_payer(
-                        &*vec![liquidate_instruction],
-                        Some(&payer.pubkey()),
-                        &vec![&payer],
-                        client.get_latest_blockhash().unwrap(),
-                    );
-                    info!("liquidating: {:?}", user.0,);
-                    info!(
-                        "result: {:?}",
-                        client.send_transaction(&liquidate_transaction)
+                    // expected payout: total open notional times the
+                    // protocol's penalty share for this liquidation type;
+                    // also track the mint of whichever position contributes
+                    // the most notional, since that's the collateral a
+                    // flash-loan liquidation will need to rebalance back to
+                    // quote
+                    let mut total_notional: u128 = 0;
+                    let mut seized_mint: Option<Pubkey> = None;
+                    let mut seized_notional: u128 = 0;
+                    for position in user_positions.borrow().positions {
+                        if position.base_asset_amount == 0 {
+                            continue;
+                        }
+                        let market =
+                            markets_account.borrow().markets[position.market_index as usize];
+                        if let Some(oracle) = oracles.iter().find(|o| *o.key == market.amm.oracle)
+                        {
+                            if let Ok(oracle_price) = market.amm.get_oracle_price(oracle, slot) {
+                                let notional = position.base_asset_amount.unsigned_abs() as u128
+                                    * oracle_price.price as u128;
+                                total_notional = total_notional.saturating_add(notional);
+                                if notional > seized_notional {
+                                    seized_notional = notional;
+                                    seized_mint = Some(market.amm.base_asset_mint);
+                                }
+                            }
+                        }
+                    }
+                    let (penalty_numerator, penalty_denominator) =
+                        if liquidation_status.liquidation_type == LiquidationType::FULL {
+                            (
+                                state.1.full_liquidation_penalty_percentage_numerator,
+                                state.1.full_liquidation_penalty_percentage_denominator,
+                            )
+                        } else {
+                            (
+                                state.1.partial_liquidation_penalty_percentage_numerator,
+                                state.1.partial_liquidation_penalty_percentage_denominator,
+                            )
+                        };
+                    let expected_payout = scheduler::estimate_payout(
+                        total_notional,
+                        penalty_numerator as u128,
+                        penalty_denominator as u128,
                     );
 
-                    user.1 =
-                        User::try_deserialize(&mut &*client.get_account_data(&user.0).unwrap())
-                            .unwrap();
-                }
+                    // with --flash-loan, borrow the quote asset to cover the
+                    // liquidation, rebalance the seized collateral back to
+                    // quote through Jupiter, and repay -- all in one
+                    // transaction -- instead of requiring pre-funded
+                    // collateral
+                    let instructions = match &flash_loan_accounts {
+                        Some(flash_loan_accounts) => match seized_mint {
+                            Some(seized_mint) => match flash_loan::fetch_route(
+                                seized_mint,
+                                flash_loan_accounts.quote_mint,
+                                flash_loan_accounts.amount,
+                                payer.pubkey(),
+                            ) {
+                                Ok(route) => Some(flash_loan::build_instructions(
+                                    flash_loan_accounts.amount,
+                                    flash_loan_accounts.repay_amount(),
+                                    flash_loan_accounts.reserve,
+                                    flash_loan_accounts.reserve_liquidity_supply,
+                                    flash_loan_accounts.fee_receiver,
+                                    flash_loan_accounts.lending_market,
+                                    flash_loan_accounts.lending_market_authority,
+                                    flash_loan_accounts.liquidator_liquidity_account,
+                                    payer.pubkey(),
+                                    liquidate_instruction,
+                                    route,
+                                )),
+                                Err(err) => {
+                                    warn!("failed to fetch jupiter route, skipping: {:?}", err);
+                                    None
+                                }
+                            },
+                            None => {
+                                warn!(
+                                    "no seized collateral mint found for {}, skipping flash loan liquidation",
+                                    user.0
+                                );
+                                None
+                            }
+                        },
+                        None => Some(vec![liquidate_instruction]),
+                    };
 
-                Some(liquidation_status.margin_ratio)
+                    instructions.map(|instructions| scheduler::LiquidationCandidate {
+                        user: user.0,
+                        margin_ratio: liquidation_status.margin_ratio,
+                        expected_payout,
+                        instructions,
+                    })
+                } else {
+                    None
+                };
+
+                Some((liquidation_status.margin_ratio, candidate))
             })
-            .min();
+            .collect();
+
+        let min_margin = slot_results.iter().map(|(margin, _)| *margin).min();
         if let Some(min_margin) = min_margin {
             debug!("min margin: {:?}", min_margin);
         }
+
+        // rank liquidatable users by expected payout, keep the top
+        // --max-liquidations-per-slot, and only send the ones that
+        // simulate cleanly (and, with --flash-loan, simulate profitably)
+        let candidates: Vec<scheduler::LiquidationCandidate> = slot_results
+            .into_iter()
+            .filter_map(|(_, candidate)| candidate)
+            .collect();
+        let candidates = scheduler::rank_and_cap(candidates, args.max_liquidations_per_slot);
+
+        candidates.par_iter().for_each(|candidate| {
+            let liquidate_transaction = match lookup_table::build_transaction(
+                &candidate.instructions,
+                &payer,
+                recent_blockhash,
+                lookup_table_account.as_ref(),
+            ) {
+                Ok(liquidate_transaction) => liquidate_transaction,
+                Err(err) => {
+                    info!(
+                        "failed to build liquidate transaction for {}: {:?}",
+                        candidate.user, err
+                    );
+                    return;
+                }
+            };
+
+            let profitable = match &flash_loan_accounts {
+                Some(flash_loan_accounts) => {
+                    let pre_balance = match client.get_token_account_balance(
+                        &flash_loan_accounts.liquidator_liquidity_account,
+                    ) {
+                        Ok(balance) => balance.amount.parse::<u64>().unwrap_or(0),
+                        Err(err) => {
+                            warn!(
+                                "failed to fetch pre-liquidation balance for {}: {:?}",
+                                candidate.user, err
+                            );
+                            return;
+                        }
+                    };
+                    let simulation = match liquidate_transaction.simulate_with_accounts(
+                        &client,
+                        &[flash_loan_accounts.liquidator_liquidity_account],
+                    ) {
+                        Ok(simulation) => simulation,
+                        Err(err) => {
+                            debug!(
+                                "skipping {}: simulation request failed: {:?}",
+                                candidate.user, err
+                            );
+                            return;
+                        }
+                    };
+                    flash_loan::check_profit(
+                        &simulation,
+                        pre_balance,
+                        flash_loan_accounts.repay_amount(),
+                        args.min_profit_bps,
+                    )
+                }
+                None => {
+                    let simulation = match liquidate_transaction.simulate(&client) {
+                        Ok(simulation) => simulation,
+                        Err(err) => {
+                            debug!(
+                                "skipping {}: simulation request failed: {:?}",
+                                candidate.user, err
+                            );
+                            return;
+                        }
+                    };
+                    // no flash loan means no swap to price; estimate net
+                    // realized reward as the expected payout, converted out
+                    // of Drift's fixed-point precision into native quote
+                    // units, against a conservative flat transaction fee,
+                    // skipping anything that simulates to a loss
+                    simulation.err.is_none()
+                        && scheduler::payout_in_quote_units(candidate.expected_payout)
+                            > ESTIMATED_TRANSACTION_FEE as u128
+                }
+            };
+            if !profitable {
+                debug!("skipping liquidation of {}: simulated loss or error", candidate.user);
+                return;
+            }
+
+            info!(
+                "liquidating {} (expected payout {}, margin ratio {})",
+                candidate.user, candidate.expected_payout, candidate.margin_ratio
+            );
+            telemetry.record_liquidation_attempt(candidate.margin_ratio);
+            match liquidate_transaction.send(&client) {
+                Ok(signature) => {
+                    info!("result: {:?}", signature);
+                    transaction_tracker.track(
+                        candidate.user,
+                        tx_sender::Action::Liquidate,
+                        signature,
+                        slot,
+                        candidate.margin_ratio,
+                        None,
+                        candidate.expected_payout,
+                    );
+                }
+                Err(err) => info!("result: {:?}", err),
+            }
+        });
+        telemetry.record_loop_latency(start.elapsed().as_millis() as u64);
         info!("loaded slot {:?} in {:?}", slot, start.elapsed());
     }
 }
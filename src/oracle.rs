@@ -0,0 +1,43 @@
+//! Oracle staleness/confidence validation, so a bad oracle is skipped for
+//! the slot instead of crashing the liquidator.
+
+use clearing_house::state::{market::Market, state::OracleGuardRails};
+use solana_sdk::account_info::AccountInfo;
+
+/// Checks `oracle_account_info` against `market`'s AMM for the current
+/// `slot`, rejecting it if it is older than `max_staleness_slots` (or the
+/// protocol's own `slots_before_stale` guard rail, whichever is stricter)
+/// or if its confidence interval exceeds `oracle_guard_rails`.
+pub fn validate_oracle(
+    oracle_account_info: &AccountInfo,
+    market: &Market,
+    slot: u64,
+    max_staleness_slots: i64,
+    oracle_guard_rails: &OracleGuardRails,
+) -> Result<(), String> {
+    let oracle_price = market
+        .amm
+        .get_oracle_price(oracle_account_info, slot)
+        .map_err(|err| format!("failed to read oracle price: {:?}", err))?;
+
+    if !oracle_price.has_sufficient_number_of_data_points {
+        return Err("oracle has insufficient data points".to_string());
+    }
+
+    let staleness_limit = max_staleness_slots.min(oracle_guard_rails.validity.slots_before_stale);
+    if oracle_price.delay > staleness_limit {
+        return Err(format!(
+            "oracle stale by {} slots (limit {})",
+            oracle_price.delay, staleness_limit
+        ));
+    }
+
+    if oracle_price.confidence > oracle_guard_rails.validity.confidence_interval_max_size {
+        return Err(format!(
+            "oracle confidence {} exceeds guard rail {}",
+            oracle_price.confidence, oracle_guard_rails.validity.confidence_interval_max_size
+        ));
+    }
+
+    Ok(())
+}
@@ -0,0 +1,61 @@
+//! Ranks liquidatable users by expected payout and caps how many are sent
+//! per slot, so the most valuable targets go first.
+
+use log::debug;
+use solana_sdk::{instruction::Instruction, pubkey::Pubkey};
+
+/// Drift v1's fixed-point scale for `base_asset_amount`.
+const AMM_RESERVE_PRECISION: u128 = 10_000_000_000_000;
+/// Drift v1's fixed-point scale for `oracle_price.price`.
+const MARK_PRICE_PRECISION: u128 = 10_000_000_000;
+/// Fixed-point scale of native quote asset amounts (e.g. USDC's 6 decimals).
+const QUOTE_PRECISION: u128 = 1_000_000;
+
+/// A user that tripped `liquidation_type != NONE` this slot, along with the
+/// instructions that would liquidate them and the estimated reward for
+/// doing so, ranked before anything is sent.
+pub struct LiquidationCandidate {
+    pub user: Pubkey,
+    pub margin_ratio: u128,
+    pub expected_payout: u128,
+    pub instructions: Vec<Instruction>,
+}
+
+/// Estimates the liquidator's reward for liquidating a user as the user's
+/// total open notional times the protocol's penalty share for this
+/// liquidation type, which is already net of the portion the protocol's
+/// insurance fund keeps.
+pub fn estimate_payout(
+    total_notional: u128,
+    penalty_numerator: u128,
+    penalty_denominator: u128,
+) -> u128 {
+    total_notional.saturating_mul(penalty_numerator) / penalty_denominator.max(1)
+}
+
+/// Converts an `estimate_payout` result out of Drift's combined
+/// base-amount/oracle-price fixed-point precision into a native quote asset
+/// amount, so it can be compared against a lamports-denominated fee without
+/// the comparison being a no-op.
+pub fn payout_in_quote_units(expected_payout: u128) -> u128 {
+    expected_payout.saturating_mul(QUOTE_PRECISION)
+        / (AMM_RESERVE_PRECISION.saturating_mul(MARK_PRICE_PRECISION)).max(1)
+}
+
+/// Sorts `candidates` by expected payout, highest first, and drops any
+/// beyond `cap` (no cap when `cap` is zero), logging how many were dropped.
+pub fn rank_and_cap(
+    mut candidates: Vec<LiquidationCandidate>,
+    cap: usize,
+) -> Vec<LiquidationCandidate> {
+    candidates.sort_by(|a, b| b.expected_payout.cmp(&a.expected_payout));
+    if cap > 0 && candidates.len() > cap {
+        debug!(
+            "ranked {} liquidation candidates, dropping {} below --max-liquidations-per-slot",
+            candidates.len(),
+            candidates.len() - cap
+        );
+        candidates.truncate(cap);
+    }
+    candidates
+}
@@ -0,0 +1,158 @@
+//! Appends a JSONL event per crank/liquidation whose transaction reaches the
+//! tracker's commitment (--telemetry-path) and serves the same activity as
+//! Prometheus counters/gauges (--metrics-port). Sends that fail outright and
+//! transactions the tracker gives up on are not written as events.
+
+use log::warn;
+use serde::Serialize;
+use std::{
+    fs::OpenOptions,
+    io::Write,
+    net::SocketAddr,
+    sync::{
+        atomic::{AtomicI64, AtomicU64, Ordering},
+        Arc, Mutex,
+    },
+    thread,
+    time::{SystemTime, UNIX_EPOCH},
+};
+
+/// One crank or liquidation transaction that reached the tracker's
+/// commitment, successful or failed on-chain (`confirmed`), appended as a
+/// line of JSON to the telemetry file when `--telemetry-path` is set.
+#[derive(Debug, Serialize)]
+pub struct LiquidationEvent {
+    pub slot: u64,
+    pub timestamp: i64,
+    pub user: String,
+    pub action: String,
+    pub market_index: Option<u64>,
+    pub margin_ratio: u128,
+    pub signature: String,
+    pub confirmed: bool,
+}
+
+pub fn unix_timestamp() -> i64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|duration| duration.as_secs() as i64)
+        .unwrap_or(0)
+}
+
+/// Durable event log plus in-memory counters/gauges. Always tracks in
+/// memory; only appends to disk and serves `/metrics` when the
+/// corresponding CLI flag is set.
+pub struct Telemetry {
+    file: Option<Mutex<std::fs::File>>,
+    liquidations_attempted: AtomicU64,
+    liquidations_confirmed: AtomicU64,
+    total_reward: AtomicU64,
+    min_margin_ratio: AtomicI64,
+    loop_latency_ms: AtomicU64,
+}
+
+impl Telemetry {
+    pub fn new(
+        telemetry_path: Option<&str>,
+        metrics_port: Option<u16>,
+    ) -> std::io::Result<Arc<Self>> {
+        let file = match telemetry_path {
+            Some(path) => Some(Mutex::new(
+                OpenOptions::new().create(true).append(true).open(path)?,
+            )),
+            None => None,
+        };
+
+        let telemetry = Arc::new(Self {
+            file,
+            liquidations_attempted: AtomicU64::new(0),
+            liquidations_confirmed: AtomicU64::new(0),
+            total_reward: AtomicU64::new(0),
+            min_margin_ratio: AtomicI64::new(i64::MAX),
+            loop_latency_ms: AtomicU64::new(0),
+        });
+
+        if let Some(port) = metrics_port {
+            spawn_metrics_server(telemetry.clone(), port);
+        }
+
+        Ok(telemetry)
+    }
+
+    /// Appends `event` to the telemetry file, if one is configured.
+    pub fn record_event(&self, event: &LiquidationEvent) {
+        let file = match &self.file {
+            Some(file) => file,
+            None => return,
+        };
+        match serde_json::to_string(event) {
+            Ok(line) => {
+                let mut file = file.lock().unwrap();
+                if let Err(err) = writeln!(file, "{}", line) {
+                    warn!("failed to append telemetry event: {:?}", err);
+                }
+            }
+            Err(err) => warn!("failed to serialize telemetry event: {:?}", err),
+        }
+    }
+
+    pub fn record_liquidation_attempt(&self, margin_ratio: u128) {
+        self.liquidations_attempted.fetch_add(1, Ordering::Relaxed);
+        self.min_margin_ratio
+            .fetch_min(margin_ratio as i64, Ordering::Relaxed);
+    }
+
+    pub fn record_liquidation_confirmed(&self, reward: u64) {
+        self.liquidations_confirmed.fetch_add(1, Ordering::Relaxed);
+        self.total_reward.fetch_add(reward, Ordering::Relaxed);
+    }
+
+    pub fn record_loop_latency(&self, latency_ms: u64) {
+        self.loop_latency_ms.store(latency_ms, Ordering::Relaxed);
+    }
+
+    fn render_prometheus(&self) -> String {
+        let min_margin_ratio = self.min_margin_ratio.load(Ordering::Relaxed);
+        format!(
+            "# HELP liquidator_liquidations_attempted Total liquidation attempts submitted\n\
+             # TYPE liquidator_liquidations_attempted counter\n\
+             liquidator_liquidations_attempted {}\n\
+             # HELP liquidator_liquidations_confirmed Total liquidations confirmed on-chain\n\
+             # TYPE liquidator_liquidations_confirmed counter\n\
+             liquidator_liquidations_confirmed {}\n\
+             # HELP liquidator_total_reward Cumulative estimated reward of confirmed liquidations, in native quote units\n\
+             # TYPE liquidator_total_reward counter\n\
+             liquidator_total_reward {}\n\
+             # HELP liquidator_min_margin_ratio Minimum margin ratio among submitted liquidation attempts\n\
+             # TYPE liquidator_min_margin_ratio gauge\n\
+             liquidator_min_margin_ratio {}\n\
+             # HELP liquidator_loop_latency_ms Most recent per-slot loop latency\n\
+             # TYPE liquidator_loop_latency_ms gauge\n\
+             liquidator_loop_latency_ms {}\n",
+            self.liquidations_attempted.load(Ordering::Relaxed),
+            self.liquidations_confirmed.load(Ordering::Relaxed),
+            self.total_reward.load(Ordering::Relaxed),
+            if min_margin_ratio == i64::MAX { 0 } else { min_margin_ratio },
+            self.loop_latency_ms.load(Ordering::Relaxed),
+        )
+    }
+}
+
+fn spawn_metrics_server(telemetry: Arc<Telemetry>, port: u16) {
+    thread::spawn(move || {
+        let addr: SocketAddr = ([0, 0, 0, 0], port).into();
+        let server = match tiny_http::Server::http(addr) {
+            Ok(server) => server,
+            Err(err) => {
+                warn!("failed to bind metrics server on {}: {:?}", addr, err);
+                return;
+            }
+        };
+        for request in server.incoming_requests() {
+            let response = tiny_http::Response::from_string(telemetry.render_prometheus());
+            if let Err(err) = request.respond(response) {
+                warn!("failed to respond to metrics request: {:?}", err);
+            }
+        }
+    });
+}
@@ -0,0 +1,188 @@
+//! Transaction confirmation, dedup, and bounded retry per `(user, action)`,
+//! so a dropped or blockhash-expired send doesn't silently vanish or get
+//! resubmitted on top of a still-inflight attempt.
+
+use crate::telemetry::{self, Telemetry};
+use log::{debug, info, warn};
+use solana_client::rpc_client::RpcClient;
+use solana_sdk::{commitment_config::CommitmentConfig, pubkey::Pubkey, signature::Signature};
+use std::{
+    collections::HashMap,
+    sync::Mutex,
+    time::{Duration, Instant},
+};
+
+/// The kind of action a tracked transaction performs for a user, the other
+/// half of the dedup key so a crank and a liquidate for the same user don't
+/// suppress each other.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Action {
+    FillOrder,
+    Liquidate,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum PendingState {
+    /// Submitted and not yet confirmed; suppress resubmission.
+    Unconfirmed,
+    /// Timed out waiting for confirmation; safe to resubmit with a fresh
+    /// blockhash, but the attempt count is kept for the retry bound.
+    AwaitingRetry,
+}
+
+struct PendingTransaction {
+    signature: Signature,
+    state: PendingState,
+    submitted_at: Instant,
+    attempts: u32,
+    slot: u64,
+    margin_ratio: u128,
+    market_index: Option<u64>,
+    expected_payout: u128,
+}
+
+/// Tracks in-flight transactions per `(user, action)` so a user isn't
+/// resubmitted while a prior attempt is still unconfirmed, and bounds how
+/// many times a timed-out transaction may be retried.
+pub struct TransactionTracker {
+    pending: Mutex<HashMap<(Pubkey, Action), PendingTransaction>>,
+    commitment: CommitmentConfig,
+    max_retries: u32,
+    confirmation_timeout: Duration,
+}
+
+impl TransactionTracker {
+    pub fn new(
+        commitment: CommitmentConfig,
+        max_retries: u32,
+        confirmation_timeout: Duration,
+    ) -> Self {
+        Self {
+            pending: Mutex::new(HashMap::new()),
+            commitment,
+            max_retries,
+            confirmation_timeout,
+        }
+    }
+
+    /// Whether `(user, action)` has an unconfirmed transaction inflight and
+    /// should be skipped rather than resubmitted this slot.
+    pub fn is_pending(&self, user: Pubkey, action: Action) -> bool {
+        matches!(
+            self.pending.lock().unwrap().get(&(user, action)),
+            Some(PendingTransaction {
+                state: PendingState::Unconfirmed,
+                ..
+            })
+        )
+    }
+
+    /// Records a freshly submitted signature for `(user, action)`, carrying
+    /// the attempt count forward if this is a retry. `slot`, `margin_ratio`,
+    /// `market_index` and `expected_payout` are carried along purely so the
+    /// telemetry that [`Self::poll`] records on confirmation is
+    /// self-contained (`expected_payout` feeds the reward counter, not the
+    /// event); `expected_payout` is meaningless for `FillOrder` and should be
+    /// passed as `0`.
+    #[allow(clippy::too_many_arguments)]
+    pub fn track(
+        &self,
+        user: Pubkey,
+        action: Action,
+        signature: Signature,
+        slot: u64,
+        margin_ratio: u128,
+        market_index: Option<u64>,
+        expected_payout: u128,
+    ) {
+        let mut pending = self.pending.lock().unwrap();
+        let attempts = match pending.get(&(user, action)) {
+            Some(prior) => prior.attempts + 1,
+            None => 1,
+        };
+        pending.insert(
+            (user, action),
+            PendingTransaction {
+                signature,
+                state: PendingState::Unconfirmed,
+                submitted_at: Instant::now(),
+                attempts,
+                slot,
+                margin_ratio,
+                market_index,
+                expected_payout,
+            },
+        );
+    }
+
+    /// Polls `getSignatureStatuses` for every tracked transaction. Confirmed
+    /// transactions are logged, recorded to `telemetry`, and dropped from
+    /// the map; transactions that time out move to `AwaitingRetry` so the
+    /// next pass is free to resubmit them, up to `max_retries`.
+    pub fn poll(&self, client: &RpcClient, telemetry: &Telemetry) {
+        let mut pending = self.pending.lock().unwrap();
+        if pending.is_empty() {
+            return;
+        }
+
+        let signatures: Vec<Signature> = pending.values().map(|tx| tx.signature).collect();
+        let statuses = match client.get_signature_statuses(&signatures) {
+            Ok(response) => response.value,
+            Err(err) => {
+                warn!("failed to poll signature statuses: {:?}", err);
+                return;
+            }
+        };
+        let mut statuses_by_signature: HashMap<Signature, _> =
+            signatures.into_iter().zip(statuses).collect();
+
+        pending.retain(|(user, action), tx| {
+            match statuses_by_signature.remove(&tx.signature).flatten() {
+                Some(status) if status.satisfies_commitment(self.commitment) => {
+                    let confirmed = status.err.is_none();
+                    match &status.err {
+                        Some(err) => warn!("{:?} for {} failed: {:?}", action, user, err),
+                        None => info!("confirmed {:?} for {} ({})", action, user, tx.signature),
+                    }
+                    telemetry.record_event(&telemetry::LiquidationEvent {
+                        slot: tx.slot,
+                        timestamp: telemetry::unix_timestamp(),
+                        user: user.to_string(),
+                        action: format!("{:?}", action),
+                        market_index: tx.market_index,
+                        margin_ratio: tx.margin_ratio,
+                        signature: tx.signature.to_string(),
+                        confirmed,
+                    });
+                    if *action == Action::Liquidate && confirmed {
+                        let reward = u64::try_from(crate::scheduler::payout_in_quote_units(
+                            tx.expected_payout,
+                        ))
+                        .unwrap_or(u64::MAX);
+                        telemetry.record_liquidation_confirmed(reward);
+                    }
+                    false
+                }
+                _ => {
+                    if tx.state == PendingState::Unconfirmed
+                        && tx.submitted_at.elapsed() > self.confirmation_timeout
+                    {
+                        if tx.attempts > self.max_retries {
+                            warn!(
+                                "giving up on {:?} for {} after {} attempts",
+                                action, user, tx.attempts
+                            );
+                            return false;
+                        }
+                        debug!(
+                            "{:?} for {} unconfirmed after {:?}, allowing retry",
+                            action, user, self.confirmation_timeout
+                        );
+                        tx.state = PendingState::AwaitingRetry;
+                    }
+                    true
+                }
+            }
+        });
+    }
+}